@@ -1,64 +1,559 @@
+use std::borrow::Cow;
+
 use nom::branch::alt;
+use nom::bytes::complete::escaped_transform;
 use nom::bytes::complete::is_not;
 use nom::bytes::complete::tag;
 use nom::character::complete::alphanumeric1;
+use nom::character::complete::char;
+use nom::character::complete::digit1;
+use nom::character::complete::line_ending;
 use nom::character::complete::space0;
+use nom::combinator::all_consuming;
+use nom::combinator::cut;
+use nom::combinator::eof;
 use nom::combinator::map;
+use nom::combinator::map_res;
 use nom::combinator::opt;
+use nom::combinator::peek;
+use nom::combinator::recognize;
+use nom::combinator::value;
+use nom::error::context;
+use nom::error::ContextError;
+use nom::error::ErrorKind;
+use nom::error::FromExternalError;
+use nom::error::ParseError;
+use nom::multi::fold_many0;
 use nom::multi::many0;
 use nom::sequence::delimited;
+use nom::sequence::pair;
 use nom::sequence::preceded;
+use nom::sequence::terminated;
 use nom::sequence::tuple;
 use nom::IResult;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct RawCall<'a> {
     pub name: &'a str,
-    pub args: Vec<&'a str>,
+    pub args: Vec<Cow<'a, str>>,
+}
+
+/// A parse error that remembers where in the input it happened, so a
+/// caller can report the offending command (via [`ScriptError::offset`]
+/// and [`ScriptError::line`]) instead of a bare nom failure.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptError<'a> {
+    pub input: &'a str,
+    pub kind: ScriptErrorKind,
+    pub command: Option<CommandError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptErrorKind {
+    Nom(ErrorKind),
+    Context(&'static str),
+}
+
+impl<'a> ScriptError<'a> {
+    /// Byte offset of the error into `original`, the full script that was parsed.
+    pub fn offset(&self, original: &str) -> usize {
+        original.len() - self.input.len()
+    }
+
+    /// 1-based line number of the error within `original`.
+    pub fn line(&self, original: &str) -> usize {
+        original[..self.offset(original)].matches('\n').count() + 1
+    }
+}
+
+impl<'a> ParseError<&'a str> for ScriptError<'a> {
+    fn from_error_kind(input: &'a str, kind: ErrorKind) -> Self {
+        ScriptError {
+            input,
+            kind: ScriptErrorKind::Nom(kind),
+            command: None,
+        }
+    }
+
+    fn append(_input: &'a str, _kind: ErrorKind, other: Self) -> Self {
+        other
+    }
 }
 
-fn sep(input: &str) -> IResult<&str, &str> {
+impl<'a> ContextError<&'a str> for ScriptError<'a> {
+    fn add_context(input: &'a str, ctx: &'static str, other: Self) -> Self {
+        ScriptError {
+            input,
+            kind: ScriptErrorKind::Context(ctx),
+            command: other.command,
+        }
+    }
+}
+
+impl<'a> FromExternalError<&'a str, CommandError> for ScriptError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, e: CommandError) -> Self {
+        ScriptError {
+            input,
+            kind: ScriptErrorKind::Nom(kind),
+            command: Some(e),
+        }
+    }
+}
+
+impl<'a> FromExternalError<&'a str, std::num::ParseIntError> for ScriptError<'a> {
+    fn from_external_error(input: &'a str, kind: ErrorKind, _e: std::num::ParseIntError) -> Self {
+        ScriptError {
+            input,
+            kind: ScriptErrorKind::Nom(kind),
+            command: None,
+        }
+    }
+}
+
+impl std::fmt::Display for ScriptError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.command {
+            Some(command) => write!(f, "{command}"),
+            None => match self.kind {
+                ScriptErrorKind::Context(ctx) => write!(f, "expected {ctx}"),
+                ScriptErrorKind::Nom(kind) => write!(f, "{}", kind.description()),
+            },
+        }
+    }
+}
+
+impl std::error::Error for ScriptError<'_> {}
+
+fn sep(input: &str) -> IResult<&str, &str, ScriptError<'_>> {
     alt((tag(","), tag("("), tag(")")))(input)
 }
 
-fn sep_spaced(input: &str) -> IResult<&str, &str> {
+fn sep_spaced(input: &str) -> IResult<&str, &str, ScriptError<'_>> {
     delimited(space0, sep, space0)(input)
 }
 
-fn argument(input: &str) -> IResult<&str, &str> {
-    is_not(",()")(input)
+fn argument(input: &str) -> IResult<&str, &str, ScriptError<'_>> {
+    is_not(",()\r\n")(input)
 }
 
-fn argument_spaced(input: &str) -> IResult<&str, &str> {
-    map(argument, str::trim)(input)
+/// A double-quoted argument, with `\"`, `\\` and `\n` escapes, so text
+/// arguments can contain commas and parentheses (e.g. dialogue passed to
+/// `say`/`text`).
+fn quoted_argument(input: &str) -> IResult<&str, String, ScriptError<'_>> {
+    delimited(
+        char('"'),
+        map(
+            opt(escaped_transform(
+                is_not("\\\""),
+                '\\',
+                alt((
+                    value("\"", tag("\"")),
+                    value("\\", tag("\\")),
+                    value("\n", tag("n")),
+                )),
+            )),
+            Option::unwrap_or_default,
+        ),
+        char('"'),
+    )(input)
 }
 
-fn argument_maybe(input: &str) -> IResult<&str, &str> {
-    map(opt(argument_spaced), Option::unwrap_or_default)(input)
+fn argument_spaced(input: &str) -> IResult<&str, Cow<'_, str>, ScriptError<'_>> {
+    alt((
+        map(
+            preceded(
+                peek(char('"')),
+                context("quoted argument", cut(quoted_argument)),
+            ),
+            Cow::Owned,
+        ),
+        map(map(argument, str::trim), Cow::Borrowed),
+    ))(input)
 }
 
-fn sep_argument(input: &str) -> IResult<&str, &str> {
-    preceded(sep_spaced, argument_maybe)(input)
+fn argument_maybe(input: &str) -> IResult<&str, Cow<'_, str>, ScriptError<'_>> {
+    map(opt(argument_spaced), |arg| arg.unwrap_or(Cow::Borrowed("")))(input)
 }
 
-fn arguments(input: &str) -> IResult<&str, Vec<&str>> {
+fn sep_argument(input: &str) -> IResult<&str, Cow<'_, str>, ScriptError<'_>> {
+    context("argument", preceded(sep_spaced, argument_maybe))(input)
+}
+
+fn arguments(input: &str) -> IResult<&str, Vec<Cow<'_, str>>, ScriptError<'_>> {
     many0(sep_argument)(input)
 }
 
-fn call_name(input: &str) -> IResult<&str, &str> {
-    alphanumeric1(input)
+fn call_name(input: &str) -> IResult<&str, &str, ScriptError<'_>> {
+    context("command name", alphanumeric1)(input)
 }
 
-fn call(input: &str) -> IResult<&str, RawCall> {
-    map(tuple((call_name, arguments)), |(name, args)| RawCall {
-        name,
-        args,
-    })(input)
+fn call(input: &str) -> IResult<&str, RawCall<'_>, ScriptError<'_>> {
+    context(
+        "command",
+        map(tuple((call_name, arguments)), |(name, args)| RawCall {
+            name,
+            args,
+        }),
+    )(input)
+}
+
+fn typed_call(input: &str) -> IResult<&str, Command, ScriptError<'_>> {
+    map_res(call, Command::try_from)(input)
+}
+
+fn blank_line(input: &str) -> IResult<&str, &str, ScriptError<'_>> {
+    preceded(space0, line_ending)(input)
+}
+
+fn script_line(input: &str) -> IResult<&str, RawCall<'_>, ScriptError<'_>> {
+    cut(terminated(call, preceded(space0, alt((line_ending, eof)))))(input)
+}
+
+fn typed_script_line(input: &str) -> IResult<&str, Command, ScriptError<'_>> {
+    cut(terminated(
+        typed_call,
+        preceded(space0, alt((line_ending, eof))),
+    ))(input)
+}
+
+/// Runs `line` over every non-blank line of `input` in turn. Unlike
+/// `many0`, a malformed line (see the `cut` in [`script_line`] and
+/// [`typed_script_line`]) aborts the whole parse with its [`ScriptError`]
+/// instead of being silently treated as the end of the script.
+fn script_lines<'a, T>(
+    input: &'a str,
+    mut line: impl FnMut(&'a str) -> IResult<&'a str, T, ScriptError<'a>>,
+) -> IResult<&'a str, Vec<T>, ScriptError<'a>> {
+    let (mut input, _) = many0(blank_line)(input)?;
+    let mut items = Vec::new();
+    while !input.is_empty() {
+        let (rest, item) = line(input)?;
+        items.push(item);
+        let (rest, _) = many0(blank_line)(rest)?;
+        input = rest;
+    }
+    Ok((input, items))
+}
+
+/// Parses a whole command script: zero or more [`RawCall`]s, one per line.
+///
+/// Blank lines and trailing whitespace around commands are ignored, and
+/// both `\n` and `\r\n` line endings are accepted.
+pub fn script(input: &str) -> IResult<&str, Vec<RawCall<'_>>, ScriptError<'_>> {
+    script_lines(input, script_line)
+}
+
+/// Parses a whole command script straight into typed [`Command`]s,
+/// reporting bad syntax and invalid commands (e.g. wrong arity) alike as
+/// a single [`ScriptError`].
+pub fn typed_script(input: &str) -> IResult<&str, Vec<Command>, ScriptError<'_>> {
+    script_lines(input, typed_script_line)
+}
+
+/// Parses `input` into typed [`Command`]s, or a [`ScriptError`] describing
+/// what went wrong and where.
+pub fn parse_script(input: &str) -> Result<Vec<Command>, ScriptError<'_>> {
+    match typed_script(input) {
+        Ok((_, commands)) => Ok(commands),
+        Err(nom::Err::Error(err) | nom::Err::Failure(err)) => Err(err),
+        Err(nom::Err::Incomplete(_)) => unreachable!("complete parsers don't return Incomplete"),
+    }
+}
+
+/// The colour of a crewmate, as named by the `colour` argument of commands
+/// like `createcrewman`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+    Red,
+    Green,
+    Blue,
+    Cyan,
+    Yellow,
+    Purple,
+}
+
+impl TryFrom<&str> for Color {
+    type Error = CommandError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "red" => Ok(Color::Red),
+            "green" => Ok(Color::Green),
+            "blue" => Ok(Color::Blue),
+            "cyan" => Ok(Color::Cyan),
+            "yellow" => Ok(Color::Yellow),
+            "purple" => Ok(Color::Purple),
+            // Callers patch in the real index via `CommandError::at_arg`.
+            _ => Err(CommandError::InvalidColor {
+                arg: 0,
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// How a created crewmate moves, as named by the `behaviour` argument of
+/// commands like `createcrewman`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Behavior {
+    StandStill,
+    FollowPlayer,
+}
+
+impl TryFrom<&str> for Behavior {
+    type Error = CommandError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "standstill" => Ok(Behavior::StandStill),
+            "followplayer" => Ok(Behavior::FollowPlayer),
+            // Callers patch in the real index via `CommandError::at_arg`.
+            _ => Err(CommandError::InvalidBehavior {
+                arg: 0,
+                value: value.to_string(),
+            }),
+        }
+    }
+}
+
+/// A single typed script command, converted from a [`RawCall`] via
+/// [`TryFrom`].
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    CreateCrewman {
+        x: i32,
+        y: i32,
+        color: Color,
+        id: i32,
+        behavior: Behavior,
+    },
+    Say(u32),
+    Text(String),
+    EndText,
+}
+
+/// An error converting a [`RawCall`] into a typed [`Command`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandError {
+    UnknownCommand(String),
+    WrongArgCount {
+        name: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    InvalidInt {
+        arg: usize,
+        value: String,
+    },
+    InvalidColor {
+        arg: usize,
+        value: String,
+    },
+    InvalidBehavior {
+        arg: usize,
+        value: String,
+    },
+}
+
+impl CommandError {
+    /// Attaches the positional argument index this error occurred at, so
+    /// callers can report e.g. "argument 2: unknown color `magenta`".
+    fn at_arg(self, arg: usize) -> Self {
+        match self {
+            CommandError::InvalidInt { value, .. } => CommandError::InvalidInt { arg, value },
+            CommandError::InvalidColor { value, .. } => CommandError::InvalidColor { arg, value },
+            CommandError::InvalidBehavior { value, .. } => {
+                CommandError::InvalidBehavior { arg, value }
+            }
+            other => other,
+        }
+    }
+}
+
+impl std::fmt::Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::UnknownCommand(name) => write!(f, "unknown command `{name}`"),
+            CommandError::WrongArgCount {
+                name,
+                expected,
+                got,
+            } => write!(f, "`{name}` expected {expected} args, got {got}"),
+            CommandError::InvalidInt { arg, value } => {
+                write!(f, "argument {arg}: expected an integer, got `{value}`")
+            }
+            CommandError::InvalidColor { arg, value } => {
+                write!(f, "argument {arg}: unknown color `{value}`")
+            }
+            CommandError::InvalidBehavior { arg, value } => {
+                write!(f, "argument {arg}: unknown behavior `{value}`")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// An integer arithmetic expression, as found in a numeric command
+/// argument (e.g. the `160+8` in `customposition(160+8, 120-4)`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Value(i64),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Paren(Box<Expr>),
+}
+
+fn factor(input: &str) -> IResult<&str, Expr, ScriptError<'_>> {
+    delimited(
+        space0,
+        alt((
+            map(delimited(char('('), expr, char(')')), |e| {
+                Expr::Paren(Box::new(e))
+            }),
+            map_res(recognize(pair(opt(char('-')), digit1)), |s: &str| {
+                s.parse().map(Expr::Value)
+            }),
+        )),
+        space0,
+    )(input)
+}
+
+fn term(input: &str) -> IResult<&str, Expr, ScriptError<'_>> {
+    let (input, init) = factor(input)?;
+    fold_many0(
+        pair(alt((char('*'), char('/'))), factor),
+        move || init.clone(),
+        |acc, (op, val)| match op {
+            '*' => Expr::Mul(Box::new(acc), Box::new(val)),
+            '/' => Expr::Div(Box::new(acc), Box::new(val)),
+            _ => unreachable!(),
+        },
+    )(input)
+}
+
+fn expr(input: &str) -> IResult<&str, Expr, ScriptError<'_>> {
+    let (input, init) = term(input)?;
+    fold_many0(
+        pair(alt((char('+'), char('-'))), term),
+        move || init.clone(),
+        |acc, (op, val)| match op {
+            '+' => Expr::Add(Box::new(acc), Box::new(val)),
+            '-' => Expr::Sub(Box::new(acc), Box::new(val)),
+            _ => unreachable!(),
+        },
+    )(input)
+}
+
+/// Folds an [`Expr`] down to its integer value, truncating division
+/// toward zero and returning `None` on divide-by-zero or overflow.
+fn eval(expr: &Expr) -> Option<i64> {
+    match expr {
+        Expr::Value(v) => Some(*v),
+        Expr::Paren(e) => eval(e),
+        Expr::Add(a, b) => eval(a)?.checked_add(eval(b)?),
+        Expr::Sub(a, b) => eval(a)?.checked_sub(eval(b)?),
+        Expr::Mul(a, b) => eval(a)?.checked_mul(eval(b)?),
+        Expr::Div(a, b) => eval(a)?.checked_div(eval(b)?),
+    }
+}
+
+/// Parses and evaluates `input` as an integer arithmetic expression (see
+/// [`Expr`]), returning `None` if it isn't one (e.g. a color name) or it
+/// divides by zero.
+pub fn eval_arg(input: &str) -> Option<i64> {
+    let (_, parsed): (_, Expr) = all_consuming(expr)(input).ok()?;
+    eval(&parsed)
+}
+
+fn parse_int(arg: usize, value: &str) -> Result<i32, CommandError> {
+    eval_arg(value)
+        .and_then(|n| i32::try_from(n).ok())
+        .ok_or_else(|| CommandError::InvalidInt {
+            arg,
+            value: value.to_string(),
+        })
+}
+
+fn parse_uint(arg: usize, value: &str) -> Result<u32, CommandError> {
+    eval_arg(value)
+        .and_then(|n| u32::try_from(n).ok())
+        .ok_or_else(|| CommandError::InvalidInt {
+            arg,
+            value: value.to_string(),
+        })
+}
+
+/// `RawCall` arguments ending in `)` always pick up one trailing empty
+/// argument, since the grammar treats the closing paren as a separator
+/// followed by an (empty) argument. Drop it so arity checks see the
+/// arguments a script author actually wrote.
+fn normalized_args<'a>(args: &'a [Cow<'a, str>]) -> &'a [Cow<'a, str>] {
+    match args {
+        [rest @ .., last] if last.is_empty() => rest,
+        _ => args,
+    }
+}
+
+impl<'a> TryFrom<RawCall<'a>> for Command {
+    type Error = CommandError;
+
+    fn try_from(call: RawCall<'a>) -> Result<Self, Self::Error> {
+        let args = normalized_args(&call.args);
+        match call.name {
+            "createcrewman" => match args {
+                [x, y, color, id, behavior] => Ok(Command::CreateCrewman {
+                    x: parse_int(0, x)?,
+                    y: parse_int(1, y)?,
+                    color: Color::try_from(color.as_ref()).map_err(|e| e.at_arg(2))?,
+                    id: parse_int(3, id)?,
+                    behavior: Behavior::try_from(behavior.as_ref()).map_err(|e| e.at_arg(4))?,
+                }),
+                _ => Err(CommandError::WrongArgCount {
+                    name: "createcrewman",
+                    expected: 5,
+                    got: args.len(),
+                }),
+            },
+            "say" => match args {
+                [id] => Ok(Command::Say(parse_uint(0, id)?)),
+                _ => Err(CommandError::WrongArgCount {
+                    name: "say",
+                    expected: 1,
+                    got: args.len(),
+                }),
+            },
+            "text" => match args {
+                [text] => Ok(Command::Text(text.to_string())),
+                _ => Err(CommandError::WrongArgCount {
+                    name: "text",
+                    expected: 1,
+                    got: args.len(),
+                }),
+            },
+            "endtext" => match args {
+                [] => Ok(Command::EndText),
+                _ => Err(CommandError::WrongArgCount {
+                    name: "endtext",
+                    expected: 0,
+                    got: args.len(),
+                }),
+            },
+            other => Err(CommandError::UnknownCommand(other.to_string())),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::Behavior;
+    use crate::Color;
+    use crate::Command;
+    use crate::CommandError;
     use crate::RawCall;
+    use crate::ScriptErrorKind;
 
     #[test]
     fn sep() {
@@ -83,31 +578,31 @@ mod tests {
 
     #[test]
     fn argument_spaced() {
-        assert_eq!(super::argument_spaced("  hi  ,"), Ok((",", "hi")));
-        assert_eq!(super::argument_spaced("  hi  ("), Ok(("(", "hi")));
-        assert_eq!(super::argument_spaced("  hi  )"), Ok((")", "hi")));
+        assert_eq!(super::argument_spaced("  hi  ,"), Ok((",", "hi".into())));
+        assert_eq!(super::argument_spaced("  hi  ("), Ok(("(", "hi".into())));
+        assert_eq!(super::argument_spaced("  hi  )"), Ok((")", "hi".into())));
     }
 
     #[test]
     fn argument_maybe() {
-        assert_eq!(super::argument_maybe("  hi  ,"), Ok((",", "hi")));
-        assert_eq!(super::argument_maybe("  hi  ("), Ok(("(", "hi")));
-        assert_eq!(super::argument_maybe("  hi  )"), Ok((")", "hi")));
-        assert_eq!(super::argument_maybe(""), Ok(("", "")));
+        assert_eq!(super::argument_maybe("  hi  ,"), Ok((",", "hi".into())));
+        assert_eq!(super::argument_maybe("  hi  ("), Ok(("(", "hi".into())));
+        assert_eq!(super::argument_maybe("  hi  )"), Ok((")", "hi".into())));
+        assert_eq!(super::argument_maybe(""), Ok(("", "".into())));
     }
 
     #[test]
     fn sep_argument() {
-        assert_eq!(super::sep_argument(")  hi  ,"), Ok((",", "hi")));
-        assert_eq!(super::sep_argument("(  hi  ("), Ok(("(", "hi")));
-        assert_eq!(super::sep_argument(",  hi  )"), Ok((")", "hi")));
+        assert_eq!(super::sep_argument(")  hi  ,"), Ok((",", "hi".into())));
+        assert_eq!(super::sep_argument("(  hi  ("), Ok(("(", "hi".into())));
+        assert_eq!(super::sep_argument(",  hi  )"), Ok((")", "hi".into())));
     }
 
     #[test]
     fn arguments() {
         assert_eq!(
             super::arguments(")  hi  ,  hi  (  hi  )"),
-            Ok(("", vec!["hi", "hi", "hi", ""]))
+            Ok(("", vec!["hi".into(), "hi".into(), "hi".into(), "".into()]))
         );
     }
 
@@ -127,7 +622,7 @@ mod tests {
                 "",
                 RawCall {
                     name: "say",
-                    args: vec!["2", ""],
+                    args: vec!["2".into(), "".into()],
                 }
             ))
         );
@@ -147,9 +642,368 @@ mod tests {
                 "",
                 RawCall {
                     name: "createcrewman",
-                    args: vec!["0", "0", "red", "0", "followplayer"],
+                    args: vec![
+                        "0".into(),
+                        "0".into(),
+                        "red".into(),
+                        "0".into(),
+                        "followplayer".into()
+                    ],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn call_with_quoted_argument() {
+        assert_eq!(
+            super::call(r#"say("Hi, (really)!")"#),
+            Ok((
+                "",
+                RawCall {
+                    name: "say",
+                    args: vec!["Hi, (really)!".into(), "".into()],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn call_with_quoted_escapes() {
+        assert_eq!(
+            super::call(r#"text("say \"hi\"\nto the \\crew")"#),
+            Ok((
+                "",
+                RawCall {
+                    name: "text",
+                    args: vec!["say \"hi\"\nto the \\crew".into(), "".into()],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn call_with_empty_quoted_argument() {
+        assert_eq!(
+            super::call(r#"text("")"#),
+            Ok((
+                "",
+                RawCall {
+                    name: "text",
+                    args: vec!["".into(), "".into()],
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn call_with_quoted_argument_rejects_unsupported_escape() {
+        assert!(super::call(r#"say("hi \t there")"#).is_err());
+    }
+
+    #[test]
+    fn call_with_mixed_quoted_and_unquoted_arguments() {
+        assert_eq!(
+            super::call(r#"createcrewman,0,0, red,0,"followplayer""#),
+            Ok((
+                "",
+                RawCall {
+                    name: "createcrewman",
+                    args: vec![
+                        "0".into(),
+                        "0".into(),
+                        "red".into(),
+                        "0".into(),
+                        "followplayer".into(),
+                    ],
                 }
             ))
         );
     }
+
+    #[test]
+    fn script() {
+        assert_eq!(
+            super::script("say(2)\nendtext\n"),
+            Ok((
+                "",
+                vec![
+                    RawCall {
+                        name: "say",
+                        args: vec!["2".into(), "".into()],
+                    },
+                    RawCall {
+                        name: "endtext",
+                        args: vec![],
+                    },
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn script_tolerates_blank_lines_and_crlf() {
+        assert_eq!(
+            super::script("\r\n\nsay(2)\r\n\r\n  \r\nendtext  \r\n\n"),
+            Ok((
+                "",
+                vec![
+                    RawCall {
+                        name: "say",
+                        args: vec!["2".into(), "".into()],
+                    },
+                    RawCall {
+                        name: "endtext",
+                        args: vec![],
+                    },
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn script_without_trailing_newline() {
+        assert_eq!(
+            super::script("endtext"),
+            Ok((
+                "",
+                vec![RawCall {
+                    name: "endtext",
+                    args: vec![],
+                }]
+            ))
+        );
+    }
+
+    #[test]
+    fn script_empty() {
+        assert_eq!(super::script(""), Ok(("", vec![])));
+        assert_eq!(super::script("\n\n  \n"), Ok(("", vec![])));
+    }
+
+    #[test]
+    fn command_from_createcrewman() {
+        let raw = RawCall {
+            name: "createcrewman",
+            args: vec![
+                "0".into(),
+                "0".into(),
+                "red".into(),
+                "0".into(),
+                "followplayer".into(),
+            ],
+        };
+        assert_eq!(
+            Command::try_from(raw),
+            Ok(Command::CreateCrewman {
+                x: 0,
+                y: 0,
+                color: Color::Red,
+                id: 0,
+                behavior: Behavior::FollowPlayer,
+            })
+        );
+    }
+
+    #[test]
+    fn command_from_say_drops_trailing_paren_artifact() {
+        let raw = RawCall {
+            name: "say",
+            args: vec!["2".into(), "".into()],
+        };
+        assert_eq!(Command::try_from(raw), Ok(Command::Say(2)));
+    }
+
+    #[test]
+    fn command_from_endtext() {
+        let raw = RawCall {
+            name: "endtext",
+            args: vec![],
+        };
+        assert_eq!(Command::try_from(raw), Ok(Command::EndText));
+    }
+
+    #[test]
+    fn command_from_unknown_name() {
+        let raw = RawCall {
+            name: "nonexistent",
+            args: vec![],
+        };
+        assert_eq!(
+            Command::try_from(raw),
+            Err(CommandError::UnknownCommand("nonexistent".to_string()))
+        );
+    }
+
+    #[test]
+    fn command_from_wrong_arg_count() {
+        let raw = RawCall {
+            name: "say",
+            args: vec!["1".into(), "2".into(), "".into()],
+        };
+        assert_eq!(
+            Command::try_from(raw),
+            Err(CommandError::WrongArgCount {
+                name: "say",
+                expected: 1,
+                got: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn command_from_invalid_color() {
+        let raw = RawCall {
+            name: "createcrewman",
+            args: vec![
+                "0".into(),
+                "0".into(),
+                "magenta".into(),
+                "0".into(),
+                "followplayer".into(),
+            ],
+        };
+        assert_eq!(
+            Command::try_from(raw),
+            Err(CommandError::InvalidColor {
+                arg: 2,
+                value: "magenta".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn command_from_invalid_int_reports_which_arg() {
+        let bad_x = RawCall {
+            name: "createcrewman",
+            args: vec![
+                "abc".into(),
+                "0".into(),
+                "red".into(),
+                "0".into(),
+                "followplayer".into(),
+            ],
+        };
+        let bad_id = RawCall {
+            name: "createcrewman",
+            args: vec![
+                "0".into(),
+                "0".into(),
+                "red".into(),
+                "abc".into(),
+                "followplayer".into(),
+            ],
+        };
+        assert_eq!(
+            Command::try_from(bad_x),
+            Err(CommandError::InvalidInt {
+                arg: 0,
+                value: "abc".to_string(),
+            })
+        );
+        assert_eq!(
+            Command::try_from(bad_id),
+            Err(CommandError::InvalidInt {
+                arg: 3,
+                value: "abc".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parse_script_reports_wrong_arg_count() {
+        let input = "endtext\nsay(1,2)\n";
+        let err = super::parse_script(input).unwrap_err();
+        assert_eq!(err.line(input), 2);
+        assert_eq!(
+            err.command,
+            Some(CommandError::WrongArgCount {
+                name: "say",
+                expected: 1,
+                got: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_script_reports_bad_syntax() {
+        let input = "endtext\n???\n";
+        let err = super::parse_script(input).unwrap_err();
+        assert_eq!(err.line(input), 2);
+        assert!(matches!(
+            err.kind,
+            ScriptErrorKind::Context(_) | ScriptErrorKind::Nom(_)
+        ));
+    }
+
+    #[test]
+    fn eval_arg_literal() {
+        assert_eq!(super::eval_arg("160"), Some(160));
+        assert_eq!(super::eval_arg("-4"), Some(-4));
+    }
+
+    #[test]
+    fn eval_arg_precedence() {
+        assert_eq!(super::eval_arg("160+8"), Some(168));
+        assert_eq!(super::eval_arg("2+3*4"), Some(14));
+        assert_eq!(super::eval_arg("(2+3)*4"), Some(20));
+        assert_eq!(super::eval_arg(" 120 - 4 "), Some(116));
+    }
+
+    #[test]
+    fn eval_arg_division_truncates_toward_zero() {
+        assert_eq!(super::eval_arg("7/2"), Some(3));
+        assert_eq!(super::eval_arg("-7/2"), Some(-3));
+    }
+
+    #[test]
+    fn eval_arg_division_by_zero_is_none() {
+        assert_eq!(super::eval_arg("1/0"), None);
+    }
+
+    #[test]
+    fn eval_arg_overflow_is_none() {
+        assert_eq!(super::eval_arg("9223372036854775807+1"), None);
+        assert_eq!(super::eval_arg("-9223372036854775808-1"), None);
+        assert_eq!(super::eval_arg("9223372036854775807*2"), None);
+        assert_eq!(super::eval_arg("-9223372036854775808/-1"), None);
+    }
+
+    #[test]
+    fn eval_arg_rejects_non_numeric() {
+        assert_eq!(super::eval_arg("red"), None);
+        assert_eq!(super::eval_arg("followplayer"), None);
+    }
+
+    #[test]
+    fn command_from_createcrewman_with_expression() {
+        let raw = RawCall {
+            name: "createcrewman",
+            args: vec![
+                "160+8".into(),
+                "120-4".into(),
+                "red".into(),
+                "0".into(),
+                "followplayer".into(),
+            ],
+        };
+        assert_eq!(
+            Command::try_from(raw),
+            Ok(Command::CreateCrewman {
+                x: 168,
+                y: 116,
+                color: Color::Red,
+                id: 0,
+                behavior: Behavior::FollowPlayer,
+            })
+        );
+    }
+
+    #[test]
+    fn parse_script_succeeds() {
+        assert_eq!(
+            super::parse_script("say(2)\nendtext\n"),
+            Ok(vec![Command::Say(2), Command::EndText])
+        );
+    }
 }